@@ -0,0 +1,75 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Cryptographic primitives used by the extension verifiers.
+//!
+//! The concrete implementation is chosen at compile time via Cargo
+//! features, mirroring the nss/openssl/dummy backend selection used by
+//! Mozilla's `authenticator` crate. `rustcrypto` is the default backend;
+//! enabling `crypto-openssl` instead routes every signature check and
+//! hash through OpenSSL for deployments with FIPS requirements.
+
+#[cfg(feature = "crypto-openssl")]
+mod openssl;
+#[cfg(not(feature = "crypto-openssl"))]
+mod rustcrypto;
+
+#[cfg(feature = "crypto-openssl")]
+use self::openssl as backend;
+#[cfg(not(feature = "crypto-openssl"))]
+use self::rustcrypto as backend;
+
+use anyhow::{anyhow, Result};
+use const_oid::db::rfc5912::ECDSA_WITH_SHA_256;
+use der::Encodable;
+use pkcs8::AlgorithmIdentifier;
+use x509::{Certificate, SubjectPublicKeyInfo, TbsCertificate};
+
+/// Verifies a raw signature over `body`, dispatching on `alg`, using the
+/// compile-time-selected crypto backend.
+pub trait Verifier {
+    fn verify_raw(&self, body: &[u8], alg: AlgorithmIdentifier<'_>, signature: &[u8]) -> Result<()>;
+}
+
+impl Verifier for SubjectPublicKeyInfo<'_> {
+    fn verify_raw(&self, body: &[u8], alg: AlgorithmIdentifier<'_>, signature: &[u8]) -> Result<()> {
+        verify_raw_with_pubkey(self.subject_public_key.raw_bytes(), alg, body, signature)
+    }
+}
+
+impl Verifier for TbsCertificate<'_> {
+    fn verify_raw(&self, body: &[u8], alg: AlgorithmIdentifier<'_>, signature: &[u8]) -> Result<()> {
+        self.subject_public_key_info.verify_raw(body, alg, signature)
+    }
+}
+
+/// Verifies a raw signature over `body` against a bare public key, e.g. an
+/// ECDSA attestation key that is not itself wrapped in a certificate's
+/// `SubjectPublicKeyInfo`.
+pub fn verify_raw_with_pubkey(
+    pubkey: &[u8],
+    alg: AlgorithmIdentifier<'_>,
+    body: &[u8],
+    signature: &[u8],
+) -> Result<()> {
+    match alg.oid {
+        ECDSA_WITH_SHA_256 => backend::verify_p256_sha256(pubkey, body, signature),
+        oid => Err(anyhow!("unsupported signature algorithm: {oid}")),
+    }
+}
+
+/// Computes the SHA-256 digest of `data` using the compile-time-selected
+/// crypto backend.
+pub fn sha256(data: &[u8]) -> [u8; 32] {
+    backend::sha256(data)
+}
+
+impl TbsCertificate<'_> {
+    /// Verifies that `self` signed `cert`, returning `cert`'s
+    /// `TbsCertificate` on success so the caller can continue walking the
+    /// chain.
+    pub fn verify_crt<'c>(&self, cert: &'c Certificate<'c>) -> Result<&'c TbsCertificate<'c>> {
+        let body = cert.tbs_certificate.to_vec()?;
+        self.verify_raw(&body, cert.signature_algorithm.clone(), cert.signature.raw_bytes())?;
+        Ok(&cert.tbs_certificate)
+    }
+}
@@ -0,0 +1,88 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! OpenSSL-backed implementation of the verifier's crypto primitives, for
+//! deployments with FIPS validation requirements the RustCrypto backend
+//! does not meet.
+
+use anyhow::{anyhow, Result};
+use openssl::{
+    bn::BigNumContext,
+    ec::{EcGroup, EcKey, EcPoint},
+    ecdsa::EcdsaSig,
+    hash::{hash, MessageDigest},
+    nid::Nid,
+};
+
+pub fn verify_p256_sha256(pubkey: &[u8], body: &[u8], signature: &[u8]) -> Result<()> {
+    let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1)?;
+    let mut ctx = BigNumContext::new()?;
+    let point = EcPoint::from_bytes(&group, pubkey, &mut ctx)
+        .map_err(|e| anyhow!("invalid p256 public key: {e}"))?;
+    let key = EcKey::from_public_key(&group, &point)?;
+
+    let digest = hash(MessageDigest::sha256(), body)?;
+    let sig =
+        EcdsaSig::from_der(signature).map_err(|e| anyhow!("invalid ecdsa signature: {e}"))?;
+
+    if sig
+        .verify(&digest, &key)
+        .map_err(|e| anyhow!("ecdsa signature verification failed: {e}"))?
+    {
+        Ok(())
+    } else {
+        Err(anyhow!("ecdsa signature did not verify"))
+    }
+}
+
+pub fn sha256(data: &[u8]) -> [u8; 32] {
+    let digest = hash(MessageDigest::sha256(), data).expect("sha256 hashing failed");
+    let mut out = [0u8; 32];
+    out.copy_from_slice(&digest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use openssl::ec::PointConversionForm;
+
+    #[test]
+    fn sha256_matches_known_vector() {
+        let expected: [u8; 32] = [
+            0xba, 0x78, 0x16, 0xbf, 0x8f, 0x01, 0xcf, 0xea, 0x41, 0x41, 0x40, 0xde, 0x5d, 0xae,
+            0x22, 0x23, 0xb0, 0x03, 0x61, 0xa3, 0x96, 0x17, 0x7a, 0x9c, 0xb4, 0x10, 0xff, 0x61,
+            0xf2, 0x00, 0x15, 0xad,
+        ];
+        assert_eq!(sha256(b"abc"), expected);
+    }
+
+    fn generate_key() -> (EcKey<openssl::pkey::Private>, Vec<u8>) {
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+        let key = EcKey::generate(&group).unwrap();
+        let mut ctx = BigNumContext::new().unwrap();
+        let pubkey = key
+            .public_key()
+            .to_bytes(&group, PointConversionForm::UNCOMPRESSED, &mut ctx)
+            .unwrap();
+        (key, pubkey)
+    }
+
+    #[test]
+    fn verify_p256_sha256_accepts_a_valid_signature() {
+        let (key, pubkey) = generate_key();
+        let body = b"sgx test message";
+        let digest = hash(MessageDigest::sha256(), body).unwrap();
+        let sig = EcdsaSig::sign(&digest, &key).unwrap();
+
+        verify_p256_sha256(&pubkey, body, &sig.to_der().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn verify_p256_sha256_rejects_a_tampered_body() {
+        let (key, pubkey) = generate_key();
+        let digest = hash(MessageDigest::sha256(), b"sgx test message").unwrap();
+        let sig = EcdsaSig::sign(&digest, &key).unwrap();
+
+        assert!(verify_p256_sha256(&pubkey, b"tampered message", &sig.to_der().unwrap()).is_err());
+    }
+}
@@ -0,0 +1,55 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! RustCrypto-backed implementation of the verifier's crypto primitives.
+//! This is the default backend.
+
+use anyhow::{anyhow, Result};
+use p256::ecdsa::{signature::Verifier as _, Signature, VerifyingKey};
+use sha2::{Digest, Sha256};
+
+pub fn verify_p256_sha256(pubkey: &[u8], body: &[u8], signature: &[u8]) -> Result<()> {
+    let key = VerifyingKey::from_sec1_bytes(pubkey)
+        .map_err(|e| anyhow!("invalid p256 public key: {e}"))?;
+    let sig = Signature::from_der(signature).map_err(|e| anyhow!("invalid ecdsa signature: {e}"))?;
+    key.verify(body, &sig)
+        .map_err(|e| anyhow!("ecdsa signature verification failed: {e}"))
+}
+
+pub fn sha256(data: &[u8]) -> [u8; 32] {
+    Sha256::digest(data).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use p256::ecdsa::{signature::Signer, SigningKey};
+
+    #[test]
+    fn sha256_matches_known_vector() {
+        let expected: [u8; 32] = [
+            0xba, 0x78, 0x16, 0xbf, 0x8f, 0x01, 0xcf, 0xea, 0x41, 0x41, 0x40, 0xde, 0x5d, 0xae,
+            0x22, 0x23, 0xb0, 0x03, 0x61, 0xa3, 0x96, 0x17, 0x7a, 0x9c, 0xb4, 0x10, 0xff, 0x61,
+            0xf2, 0x00, 0x15, 0xad,
+        ];
+        assert_eq!(sha256(b"abc"), expected);
+    }
+
+    #[test]
+    fn verify_p256_sha256_accepts_a_valid_signature() {
+        let signing_key = SigningKey::from_bytes(&[7u8; 32]).unwrap();
+        let pubkey = signing_key.verifying_key().to_encoded_point(false);
+        let body = b"sgx test message";
+        let signature: Signature = signing_key.sign(body);
+
+        verify_p256_sha256(pubkey.as_bytes(), body, &signature.to_der()).unwrap();
+    }
+
+    #[test]
+    fn verify_p256_sha256_rejects_a_tampered_body() {
+        let signing_key = SigningKey::from_bytes(&[7u8; 32]).unwrap();
+        let pubkey = signing_key.verifying_key().to_encoded_point(false);
+        let signature: Signature = signing_key.sign(b"sgx test message");
+
+        assert!(verify_p256_sha256(pubkey.as_bytes(), b"tampered message", &signature.to_der()).is_err());
+    }
+}
@@ -1,8 +1,13 @@
 // SPDX-License-Identifier: Apache-2.0
 
 mod attestation_types;
+mod policy;
+mod tcb;
 mod types;
 
+pub use policy::{Policy, PolicyEntry};
+pub use tcb::{PckPlatform, QeIdentity, QeTcbLevel, QeTcbStatus, TcbInfo, TcbLevel, TcbStatus};
+
 use crate::crypto::*;
 
 use std::fmt::Debug;
@@ -11,9 +16,12 @@ use anyhow::{anyhow, Context, Result};
 use attestation_types::quote::Quote;
 use const_oid::db::rfc5912::ECDSA_WITH_SHA_256;
 use const_oid::ObjectIdentifier;
-use der::{Decodable, Sequence};
+use der::{Decodable, Encodable, Sequence};
 use pkcs8::AlgorithmIdentifier;
-use x509::{ext::Extension, request::CertReqInfo, Certificate, PkiPath, TbsCertificate};
+use x509::{
+    crl::CertificateList, ext::Extension, request::CertReqInfo, time::Time, Certificate, PkiPath,
+    TbsCertificate,
+};
 
 use super::ExtVerifier;
 #[derive(Clone, Debug, PartialEq, Eq, Sequence)]
@@ -24,28 +32,248 @@ pub struct Evidence<'a> {
     pub quote: &'a [u8],
 }
 
-#[derive(Clone, Debug, Default)]
-pub struct Sgx(());
+// No `Default` impl: a default-constructed `Policy` allow-lists nothing,
+// so a verifier built without an explicit policy would silently reject
+// every quote. Forcing callers through `Sgx::new` turns that footgun into
+// a compile error instead of a surprise in production.
+#[derive(Clone, Debug)]
+pub struct Sgx {
+    policy: Policy,
+    crls: Vec<Vec<u8>>,
+    tcb: Option<(TcbInfo, QeIdentity, Vec<TcbStatus>)>,
+}
 
 impl Sgx {
     const ROOT: &'static [u8] = include_bytes!("sgx.pkipath");
 
-    // This ensures that the supplied pck is rooted in our trusted chain.
+    /// Creates a verifier that additionally enforces the given
+    /// enclave-identity policy on every verified quote.
+    pub fn new(policy: Policy) -> Self {
+        Self {
+            policy,
+            crls: Vec::new(),
+            tcb: None,
+        }
+    }
+
+    /// Supplies CRLs (DER-encoded `CertificateList`s) to consult while
+    /// walking the PCK chain, e.g. the Intel Root CA CRL and the PCK
+    /// Processor/Platform CA CRLs. CRLs can be supplied directly as
+    /// configuration or fetched ahead of time from the CRL distribution
+    /// point URIs carried in the chain's certificates.
+    pub fn with_crls(mut self, crls: impl IntoIterator<Item = Vec<u8>>) -> Self {
+        self.crls.extend(crls);
+        self
+    }
+
+    /// Supplies Intel TCB Info and QE Identity collateral so that every
+    /// verified quote's platform and Quoting Enclave TCB are evaluated.
+    ///
+    /// `tcb_signing_cert_der` is the Intel TCB Signing certificate (DER);
+    /// it is verified against the same trusted root as the PCK chain, and
+    /// `tcb_info_json`/`qe_identity_json` are only accepted once their
+    /// embedded signatures are confirmed to come from that cert. Callers
+    /// cannot hand in a `TcbInfo`/`QeIdentity` that wasn't verified this
+    /// way. `allowed_statuses` lists the platform `TcbStatus` values
+    /// accepted in addition to `UpToDate`.
+    pub fn with_tcb(
+        mut self,
+        tcb_signing_cert_der: &[u8],
+        tcb_info_json: &[u8],
+        qe_identity_json: &[u8],
+        allowed_statuses: impl IntoIterator<Item = TcbStatus>,
+    ) -> Result<Self> {
+        let tcb_signing_cert =
+            Certificate::from_der(tcb_signing_cert_der).context("sgx tcb signing cert parse error")?;
+        let signer = walk_trust_chain(&tcb_signing_cert, "tcb signing cert", |issuer, cert| {
+            self.check_not_revoked(issuer, cert)
+        })?;
+
+        let tcb_info = TcbInfo::from_signed_json(signer, tcb_info_json)?;
+        let qe_identity = QeIdentity::from_signed_json(signer, qe_identity_json)?;
+
+        self.tcb = Some((tcb_info, qe_identity, allowed_statuses.into_iter().collect()));
+        Ok(self)
+    }
+
+    // This ensures that the supplied pck is rooted in our trusted chain and
+    // that no certificate along that chain has been revoked.
     fn is_trusted<'c>(&self, pck: &'c Certificate<'c>) -> Result<&'c TbsCertificate<'c>> {
-        let path = PkiPath::from_der(Self::ROOT)?;
+        walk_trust_chain(pck, "pck", |issuer, cert| self.check_not_revoked(issuer, cert))
+    }
+}
+
+// Walks the trusted Intel SGX root PKI path, calling `on_hop` with each
+// (issuer, cert) pair along the way so the caller can layer its own
+// per-hop checks (e.g. CRL revocation) on top of the walk, then confirms
+// that `leaf` was issued directly by some certificate in that trusted
+// path. Checking every path certificate rather than only the last one
+// matters because Intel doesn't issue every kind of leaf the same number
+// of hops below the root: a PCK cert is issued through the intermediate
+// PCK Platform/Processor CA, but the TCB Signing cert is issued directly
+// by the Root CA. Shared by `Sgx::is_trusted`, for the long-lived PCK
+// chain, and by `Sgx::with_tcb`, which verifies the TCB Signing cert's
+// chain fresh on every call instead of keeping it in `Sgx` state.
+fn walk_trust_chain<'c>(
+    leaf: &'c Certificate<'c>,
+    what: &str,
+    mut on_hop: impl FnMut(&TbsCertificate<'_>, &Certificate<'_>) -> Result<()>,
+) -> Result<&'c TbsCertificate<'c>> {
+    let path = PkiPath::from_der(Sgx::ROOT)?;
+
+    let mut signer = Some(&path.0[0].tbs_certificate);
+    let mut trusted = Vec::with_capacity(path.0.len());
+    for cert in &path.0 {
+        let issuer = signer;
+        signer = signer.and_then(|s| s.verify_crt(cert).ok());
+
+        if let Some(issuer) = issuer {
+            on_hop(issuer, cert)?;
+        }
+
+        // Only a signer that was itself confirmed above is trusted to
+        // vouch for `leaf` below; a broken hop partway through the path
+        // must not leave later, unverified certificates eligible.
+        if let Some(tbs) = signer {
+            trusted.push(tbs);
+        }
+    }
 
-        let mut signer = Some(&path.0[0].tbs_certificate);
-        for cert in path.0.iter().chain([pck].into_iter()) {
-            signer = signer.and_then(|s| s.verify_crt(cert).ok());
+    // Try the most specific (deepest) trusted issuer first, since that
+    // matches the common case of a leaf chained through every hop.
+    for issuer in trusted.iter().rev() {
+        if let Ok(tbs) = issuer.verify_crt(leaf) {
+            on_hop(issuer, leaf)?;
+            return Ok(tbs);
         }
+    }
+
+    Err(anyhow!("sgx {what} is untrusted"))
+}
+
+impl Sgx {
+    // Finds the CRL(s) issued by `issuer` among the CRLs supplied via
+    // `with_crls` and checks that `cert`'s serial number is not on any
+    // valid one of them. A certificate whose issuer has no matching CRL is
+    // not rejected; CRL coverage is opt-in via the CRLs the caller
+    // supplies. A CRL that fails to parse or fails its signature check
+    // is an error. A CRL that is outside its validity period is skipped
+    // rather than treated as authoritative, e.g. during a rollover where
+    // both the old and new CRL for an issuer are present.
+    fn check_not_revoked(&self, issuer: &TbsCertificate<'_>, cert: &Certificate<'_>) -> Result<()> {
+        let now = Time::try_from(std::time::SystemTime::now())
+            .context("sgx: failed to determine current time")?;
+
+        for der in &self.crls {
+            let crl = CertificateList::from_der(der).context("sgx crl parse error")?;
+
+            if crl.tbs_cert_list.issuer != issuer.subject {
+                continue;
+            }
+
+            issuer
+                .subject_public_key_info
+                .verify_raw(
+                    &crl.tbs_cert_list.to_vec().context("sgx crl re-encode error")?,
+                    crl.signature_algorithm.clone(),
+                    crl.signature.raw_bytes(),
+                )
+                .context("sgx crl has an invalid signature")?;
 
-        if let Some(signer) = signer {
-            if signer == &pck.tbs_certificate {
-                return Ok(&pck.tbs_certificate);
+            if !crl_is_valid_now(now, crl.tbs_cert_list.this_update, crl.tbs_cert_list.next_update) {
+                continue;
+            }
+
+            let revoked = crl
+                .tbs_cert_list
+                .revoked_certificates
+                .iter()
+                .flatten()
+                .map(|r| r.user_certificate);
+            if serial_is_revoked(cert.tbs_certificate.serial_number, revoked) {
+                return Err(anyhow!("sgx certificate has been revoked"));
             }
         }
 
-        Err(anyhow!("sgx pck is untrusted"))
+        Ok(())
+    }
+}
+
+// A CRL that hasn't reached its `thisUpdate` yet, or that has passed its
+// `nextUpdate`, is skipped rather than treated as authoritative: during a
+// rollover, both the old and new CRL for an issuer may be present, and we
+// want to consult whichever one is actually current.
+fn crl_is_valid_now(now: Time, this_update: Time, next_update: Option<Time>) -> bool {
+    if now < this_update {
+        return false;
+    }
+
+    if let Some(next_update) = next_update {
+        if now > next_update {
+            return false;
+        }
+    }
+
+    true
+}
+
+fn serial_is_revoked<'a>(
+    serial: der::asn1::UIntBytes<'a>,
+    mut revoked: impl Iterator<Item = der::asn1::UIntBytes<'a>>,
+) -> bool {
+    revoked.any(|r| r == serial)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, SystemTime};
+
+    fn at(offset_secs: i64) -> Time {
+        let now = SystemTime::now();
+        let when = if offset_secs >= 0 {
+            now + Duration::from_secs(offset_secs as u64)
+        } else {
+            now - Duration::from_secs((-offset_secs) as u64)
+        };
+        Time::try_from(when).unwrap()
+    }
+
+    #[test]
+    fn crl_not_yet_valid_is_skipped() {
+        assert!(!crl_is_valid_now(at(0), at(3600), None));
+    }
+
+    #[test]
+    fn crl_past_next_update_is_skipped() {
+        assert!(!crl_is_valid_now(at(0), at(-7200), Some(at(-3600))));
+    }
+
+    #[test]
+    fn crl_within_validity_window_is_valid() {
+        assert!(crl_is_valid_now(at(0), at(-3600), Some(at(3600))));
+    }
+
+    #[test]
+    fn crl_with_no_next_update_is_valid() {
+        assert!(crl_is_valid_now(at(0), at(-3600), None));
+    }
+
+    #[test]
+    fn matching_serial_is_revoked() {
+        let serial = der::asn1::UIntBytes::new(&[1, 2, 3]).unwrap();
+        let revoked = vec![
+            der::asn1::UIntBytes::new(&[9, 9, 9]).unwrap(),
+            der::asn1::UIntBytes::new(&[1, 2, 3]).unwrap(),
+        ];
+        assert!(serial_is_revoked(serial, revoked.into_iter()));
+    }
+
+    #[test]
+    fn mismatched_serial_is_not_revoked() {
+        let serial = der::asn1::UIntBytes::new(&[1, 2, 3]).unwrap();
+        let revoked = vec![der::asn1::UIntBytes::new(&[9, 9, 9]).unwrap()];
+        assert!(!serial_is_revoked(serial, revoked.into_iter()));
     }
 }
 
@@ -84,27 +312,78 @@ impl ExtVerifier for Sgx {
 
         // Extract the quote and its signature.
         let quote = Quote::try_from(evidence.quote).context("sgx quote parse error")?;
+        let sigdata = quote.sigdata();
 
-        let body: [u8; 384] = unsafe { std::mem::transmute(*quote.body()) };
-        let signature = quote
-            .sigdata()
-            .report_sig()
-            .to_der()
-            .context("sgx quote signature parse error")?;
+        // The enclave report is signed by the quote's ECDSA attestation
+        // key, not the PCK directly.
+        let report = quote.body();
+        let body: [u8; 384] = unsafe { std::mem::transmute(*report) };
 
-        pck.verify_raw(
+        verify_raw_with_pubkey(
+            sigdata.attestation_pubkey(),
+            AlgorithmIdentifier {
+                oid: ECDSA_WITH_SHA_256,
+                parameters: None,
+            },
             &body,
+            &sigdata
+                .report_sig()
+                .to_der()
+                .context("sgx quote signature parse error")?,
+        )
+        .context("sgx quote contains invalid signature")?;
+
+        self.policy.evaluate(
+            &report.mrenclave(),
+            &report.mrsigner(),
+            report.isvprodid(),
+            report.isvsvn(),
+            report.attributes(),
+            report.miscselect(),
+            dbg,
+        )?;
+
+        // The PCK signs the QE's own report, which in turn binds the
+        // attestation key used above via its report_data.
+        let qe_report = sigdata.qe_report();
+        let qe_body: [u8; 384] = unsafe { std::mem::transmute(*qe_report) };
+
+        pck.verify_raw(
+            &qe_body,
             AlgorithmIdentifier {
                 oid: ECDSA_WITH_SHA_256,
                 parameters: None,
             },
-            &signature,
+            &sigdata
+                .qe_report_sig()
+                .to_der()
+                .context("sgx qe report signature parse error")?,
         )
-        .unwrap();
-        // .context("sgx quote contains invalid signature")?;
+        .context("sgx qe report has an invalid signature")?;
+
+        tcb::verify_key_binding(
+            &qe_report.report_data(),
+            sigdata.attestation_pubkey(),
+            sigdata.qe_auth_data(),
+        )?;
 
-        // TODO: validate report
-        if !dbg {}
+        if let Some((tcb_info, qe_identity, allowed_statuses)) = &self.tcb {
+            let platform = PckPlatform::from_tbs(pck)?;
+            let status = tcb_info.status_for(&platform)?;
+            if status != TcbStatus::UpToDate && !allowed_statuses.contains(&status) {
+                return Err(anyhow!("sgx platform tcb status {status:?} is not allowed"));
+            }
+
+            let qe_status = qe_identity.verify(
+                &qe_report.mrsigner(),
+                qe_report.isvprodid(),
+                qe_report.isvsvn(),
+                qe_report.attributes(),
+            )?;
+            if matches!(qe_status, QeTcbStatus::Revoked | QeTcbStatus::OutOfDate) {
+                return Err(anyhow!("sgx qe tcb status {qe_status:?} is not allowed"));
+            }
+        }
 
         Ok(true)
     }
@@ -0,0 +1,233 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Enclave-identity policy (Section 38.7) enforcement.
+//!
+//! An operator-supplied allow-list, keyed on MRSIGNER/MRENCLAVE and
+//! ISVPRODID/ISVSVN, plus SIGSTRUCT-style attribute and miscselect masks,
+//! that the verified report body must satisfy before a quote is accepted.
+
+use super::types::attr::{Attributes, Flags};
+use super::types::misc::MiscSelect;
+
+use anyhow::{anyhow, Result};
+use flagset::FlagSet;
+
+/// A single allow-listed enclave identity.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PolicyEntry {
+    /// The enclave signer's measurement hash (MRSIGNER).
+    pub mrsigner: [u8; 32],
+
+    /// The exact enclave measurement (MRENCLAVE). `None` accepts any
+    /// MRENCLAVE produced by `mrsigner`.
+    pub mrenclave: Option<[u8; 32]>,
+
+    /// The product ID (ISVPRODID) this entry applies to.
+    pub isvprodid: u16,
+
+    /// The minimum acceptable security version (ISVSVN).
+    pub min_isvsvn: u16,
+}
+
+impl PolicyEntry {
+    fn matches(&self, mrenclave: &[u8; 32], mrsigner: &[u8; 32], isvprodid: u16, isvsvn: u16) -> bool {
+        &self.mrsigner == mrsigner
+            && self.mrenclave.as_ref().map_or(true, |mre| mre == mrenclave)
+            && self.isvprodid == isvprodid
+            && isvsvn >= self.min_isvsvn
+    }
+}
+
+/// An operator-supplied enclave-identity policy.
+///
+/// `attributes_mask`/`attributes_expected` and `miscselect_mask`/
+/// `miscselect_expected` follow the SIGSTRUCT convention: a report passes
+/// the check when `report & mask == expected & mask`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Policy {
+    /// The allow-listed enclave identities. A report must match at least
+    /// one entry.
+    pub entries: Vec<PolicyEntry>,
+
+    pub attributes_mask: Attributes,
+    pub attributes_expected: Attributes,
+
+    pub miscselect_mask: FlagSet<MiscSelect>,
+    pub miscselect_expected: FlagSet<MiscSelect>,
+}
+
+impl Default for Policy {
+    /// The default policy allow-lists nothing, so every report is rejected
+    /// until the caller supplies a real policy.
+    fn default() -> Self {
+        Self {
+            entries: Vec::new(),
+            attributes_mask: Attributes::default(),
+            attributes_expected: Attributes::default(),
+            miscselect_mask: FlagSet::default(),
+            miscselect_expected: FlagSet::default(),
+        }
+    }
+}
+
+impl Policy {
+    /// Checks a verified report body against this policy.
+    ///
+    /// `dbg` must be `true` for the caller to accept a report with the
+    /// `DEBUG` attribute flag set; otherwise such a report is rejected
+    /// regardless of whether an entry would otherwise match.
+    #[allow(clippy::too_many_arguments)]
+    pub fn evaluate(
+        &self,
+        mrenclave: &[u8; 32],
+        mrsigner: &[u8; 32],
+        isvprodid: u16,
+        isvsvn: u16,
+        attributes: Attributes,
+        miscselect: FlagSet<MiscSelect>,
+        dbg: bool,
+    ) -> Result<()> {
+        if attributes.flags().contains(Flags::DEBUG) && !dbg {
+            return Err(anyhow!(
+                "sgx report is a debug enclave but dbg was not requested"
+            ));
+        }
+
+        if (attributes & self.attributes_mask) != (self.attributes_expected & self.attributes_mask) {
+            return Err(anyhow!("sgx report attributes do not match policy"));
+        }
+
+        if (miscselect & self.miscselect_mask) != (self.miscselect_expected & self.miscselect_mask) {
+            return Err(anyhow!("sgx report miscselect does not match policy"));
+        }
+
+        if !self
+            .entries
+            .iter()
+            .any(|e| e.matches(mrenclave, mrsigner, isvprodid, isvsvn))
+        {
+            return Err(anyhow!("sgx enclave identity not allowed by policy"));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::types::attr::XfrmWrapper;
+
+    fn entry(mrsigner: [u8; 32], isvprodid: u16, min_isvsvn: u16) -> PolicyEntry {
+        PolicyEntry {
+            mrsigner,
+            mrenclave: None,
+            isvprodid,
+            min_isvsvn,
+        }
+    }
+
+    #[test]
+    fn accepts_a_matching_entry() {
+        let mrsigner = [1u8; 32];
+        let policy = Policy {
+            entries: vec![entry(mrsigner, 7, 2)],
+            ..Policy::default()
+        };
+
+        policy
+            .evaluate(&[0u8; 32], &mrsigner, 7, 2, Attributes::default(), FlagSet::default(), false)
+            .unwrap();
+    }
+
+    #[test]
+    fn rejects_isvsvn_below_the_minimum() {
+        let mrsigner = [1u8; 32];
+        let policy = Policy {
+            entries: vec![entry(mrsigner, 7, 2)],
+            ..Policy::default()
+        };
+
+        assert!(policy
+            .evaluate(&[0u8; 32], &mrsigner, 7, 1, Attributes::default(), FlagSet::default(), false)
+            .is_err());
+    }
+
+    #[test]
+    fn rejects_an_unlisted_mrsigner() {
+        let policy = Policy {
+            entries: vec![entry([1u8; 32], 7, 2)],
+            ..Policy::default()
+        };
+
+        assert!(policy
+            .evaluate(&[0u8; 32], &[2u8; 32], 7, 2, Attributes::default(), FlagSet::default(), false)
+            .is_err());
+    }
+
+    #[test]
+    fn rejects_debug_attribute_unless_dbg_is_requested() {
+        let mrsigner = [1u8; 32];
+        let policy = Policy {
+            entries: vec![entry(mrsigner, 7, 2)],
+            ..Policy::default()
+        };
+        let debug_attrs = Attributes::new(Flags::BIT64 | Flags::DEBUG, XfrmWrapper::default());
+
+        assert!(policy
+            .evaluate(&[0u8; 32], &mrsigner, 7, 2, debug_attrs, FlagSet::default(), false)
+            .is_err());
+
+        policy
+            .evaluate(&[0u8; 32], &mrsigner, 7, 2, debug_attrs, FlagSet::default(), true)
+            .unwrap();
+    }
+
+    #[test]
+    fn rejects_attributes_outside_the_mask() {
+        let mrsigner = [1u8; 32];
+        let policy = Policy {
+            entries: vec![entry(mrsigner, 7, 2)],
+            attributes_mask: Attributes::new(Flags::KSS.into(), XfrmWrapper(FlagSet::default())),
+            attributes_expected: Attributes::new(Flags::KSS.into(), XfrmWrapper(FlagSet::default())),
+            ..Policy::default()
+        };
+
+        let without_kss = Attributes::new(Flags::BIT64.into(), XfrmWrapper::default());
+        assert!(policy
+            .evaluate(&[0u8; 32], &mrsigner, 7, 2, without_kss, FlagSet::default(), false)
+            .is_err());
+
+        let with_kss = Attributes::new(Flags::BIT64 | Flags::KSS, XfrmWrapper::default());
+        policy
+            .evaluate(&[0u8; 32], &mrsigner, 7, 2, with_kss, FlagSet::default(), false)
+            .unwrap();
+    }
+
+    #[test]
+    fn rejects_miscselect_outside_the_mask() {
+        let mrsigner = [1u8; 32];
+        let policy = Policy {
+            entries: vec![entry(mrsigner, 7, 2)],
+            miscselect_mask: MiscSelect::EXINFO.into(),
+            miscselect_expected: MiscSelect::EXINFO.into(),
+            ..Policy::default()
+        };
+
+        assert!(policy
+            .evaluate(&[0u8; 32], &mrsigner, 7, 2, Attributes::default(), FlagSet::default(), false)
+            .is_err());
+
+        policy
+            .evaluate(
+                &[0u8; 32],
+                &mrsigner,
+                7,
+                2,
+                Attributes::default(),
+                MiscSelect::EXINFO.into(),
+                false,
+            )
+            .unwrap();
+    }
+}
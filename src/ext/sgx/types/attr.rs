@@ -123,15 +123,72 @@ impl Attributes {
 
     /// Returns Attributes as a Vec<u8>
     pub fn to_vec(&self) -> Vec<u8> {
-        let mut v = Vec::new();
-        unsafe {
-            let byte_slice = core::slice::from_raw_parts(
-                &self as *const _ as *const u8,
-                core::mem::size_of::<Self>(),
-            );
-            v.extend_from_slice(byte_slice);
+        self.to_bytes().to_vec()
+    }
+
+    /// Serializes the attributes to their 16-byte wire format: the 8-byte
+    /// little-endian `flags` word followed by the 8-byte little-endian
+    /// `xfrm` word, as found in a report body or SIGSTRUCT.
+    pub fn to_bytes(&self) -> [u8; 16] {
+        // Copy the packed fields out by value first: `Attributes` is
+        // `packed(4)`, so taking a reference to an 8-byte-aligned
+        // `FlagSet` field in place (as `.bits()` would) is unsound.
+        let flags = self.flags;
+        let xfrm = self.xfrm;
+
+        let mut out = [0u8; 16];
+        out[..8].copy_from_slice(&flags.bits().to_le_bytes());
+        out[8..].copy_from_slice(&xfrm.0.bits().to_le_bytes());
+        out
+    }
+
+    /// Parses Attributes from its 16-byte wire format, rejecting unknown
+    /// `Flags`/`Xfrm` bits.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, AttributesError> {
+        Self::try_from(bytes)
+    }
+}
+
+/// Error returned when parsing raw bytes into [`Attributes`] fails.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AttributesError {
+    /// The input was not exactly 16 bytes long.
+    Length,
+    /// The `flags` word set bits not defined by [`Flags`].
+    UnknownFlags,
+    /// The `xfrm` word set bits not defined by [`Xfrm`].
+    UnknownXfrm,
+}
+
+impl core::fmt::Display for AttributesError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Length => write!(f, "attributes must be exactly 16 bytes"),
+            Self::UnknownFlags => write!(f, "attributes contain unknown flag bits"),
+            Self::UnknownXfrm => write!(f, "attributes contain unknown xfrm bits"),
         }
-        v
+    }
+}
+
+impl std::error::Error for AttributesError {}
+
+impl core::convert::TryFrom<&[u8]> for Attributes {
+    type Error = AttributesError;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        let bytes: [u8; 16] = bytes.try_into().map_err(|_| AttributesError::Length)?;
+
+        let flags_bits = u64::from_le_bytes(bytes[..8].try_into().unwrap());
+        let xfrm_bits = u64::from_le_bytes(bytes[8..].try_into().unwrap());
+
+        let flags =
+            FlagSet::<Flags>::new(flags_bits).map_err(|_| AttributesError::UnknownFlags)?;
+        let xfrm = FlagSet::<Xfrm>::new(xfrm_bits).map_err(|_| AttributesError::UnknownXfrm)?;
+
+        Ok(Self {
+            flags,
+            xfrm: XfrmWrapper(xfrm),
+        })
     }
 }
 
@@ -183,3 +240,35 @@ impl core::ops::BitXor for Attributes {
 testaso! {
     struct Attributes: 4, 16 => {}
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_through_bytes() {
+        let attrs = Attributes::new(Flags::INIT | Flags::BIT64, XfrmWrapper(Xfrm::X87 | Xfrm::SSE));
+        let bytes = attrs.to_bytes();
+        assert_eq!(Attributes::from_bytes(&bytes), Ok(attrs));
+    }
+
+    #[test]
+    fn rejects_wrong_length() {
+        assert_eq!(Attributes::from_bytes(&[0u8; 15]), Err(AttributesError::Length));
+        assert_eq!(Attributes::from_bytes(&[0u8; 17]), Err(AttributesError::Length));
+    }
+
+    #[test]
+    fn rejects_unknown_flags_bit() {
+        let mut bytes = Attributes::default().to_bytes();
+        bytes[0] |= 1 << 3; // reserved bit in the flags word
+        assert_eq!(Attributes::from_bytes(&bytes), Err(AttributesError::UnknownFlags));
+    }
+
+    #[test]
+    fn rejects_unknown_xfrm_bit() {
+        let mut bytes = Attributes::default().to_bytes();
+        bytes[9] |= 1; // bit 8 of the xfrm word, reserved
+        assert_eq!(Attributes::from_bytes(&bytes), Err(AttributesError::UnknownXfrm));
+    }
+}